@@ -1,8 +1,14 @@
-use crate::utils::{is_adjusted, iter_input_pats, snippet_opt, span_lint_and_then, type_is_unsafe_function};
+use crate::utils::{
+    higher, implements_trait, in_macro, is_adjusted, iter_input_pats, snippet_opt, span_lint_and_then,
+    type_is_unsafe_function,
+};
 use if_chain::if_chain;
+use rustc::hir::def::Res;
+use rustc::hir::intravisit::{self, NestedVisitorMap, Visitor};
 use rustc::hir::*;
 use rustc::lint::{in_external_macro, LateContext, LateLintPass, LintArray, LintContext, LintPass};
 use rustc::ty;
+use rustc::ty::adjustment::{Adjust, AutoBorrow};
 use rustc::{declare_tool_lint, lint_array};
 use rustc_errors::Applicability;
 
@@ -33,9 +39,30 @@ declare_clippy_lint! {
     "redundant closures, i.e. `|a| foo(a)` (which can be written as just `foo`)"
 }
 
+/// **What it does:** Checks for closures which only invoke a method on the
+/// closure argument and can be replaced by referencing the method directly.
+///
+/// **Why is this bad?** It's unnecessary to create the closure.
+///
+/// **Known problems:** #3071
+///
+/// **Example:**
+/// ```rust
+/// Some('a').map(|s| s.to_uppercase());
+/// ```
+/// may be rewritten as
+/// ```rust
+/// Some('a').map(char::to_uppercase);
+/// ```
+declare_clippy_lint! {
+    pub REDUNDANT_CLOSURE_FOR_METHOD_CALLS,
+    pedantic,
+    "redundant closures for method calls"
+}
+
 impl LintPass for EtaPass {
     fn get_lints(&self) -> LintArray {
-        lint_array!(REDUNDANT_CLOSURE)
+        lint_array!(REDUNDANT_CLOSURE, REDUNDANT_CLOSURE_FOR_METHOD_CALLS)
     }
 
     fn name(&self) -> &'static str {
@@ -52,7 +79,20 @@ impl<'a, 'tcx> LateLintPass<'a, 'tcx> for EtaPass {
         match expr.node {
             ExprKind::Call(_, ref args) | ExprKind::MethodCall(_, _, ref args) => {
                 for arg in args {
-                    check_closure(cx, arg)
+                    // A `vec![..]` expansion hides its elements behind a macro call; peek inside so
+                    // closures used as e.g. the mapper in `vec![f; n]` are still examined.
+                    if let Some(vec_args) = higher::vec_macro(cx, arg) {
+                        match vec_args {
+                            higher::VecArgs::Repeat(elem, _) => check_closure(cx, elem),
+                            higher::VecArgs::Vec(elems) => {
+                                for elem in elems {
+                                    check_closure(cx, elem)
+                                }
+                            },
+                        }
+                    } else {
+                        check_closure(cx, arg)
+                    }
                 }
             },
             _ => (),
@@ -62,6 +102,12 @@ impl<'a, 'tcx> LateLintPass<'a, 'tcx> for EtaPass {
 
 fn check_closure(cx: &LateContext<'_, '_>, expr: &Expr) {
     if let ExprKind::Closure(_, ref decl, eid, _, _) = expr.node {
+        // Closures coming from a macro expansion can't be rewritten with a machine-applicable
+        // suggestion, so leave them alone.
+        if in_macro(expr.span) {
+            return;
+        }
+
         let body = cx.tcx.hir().body(eid);
         let ex = &body.value;
 
@@ -71,14 +117,26 @@ fn check_closure(cx: &LateContext<'_, '_>, expr: &Expr) {
             // Not the same number of arguments, there is no way the closure is the same as the function return;
             if args.len() == decl.inputs.len();
 
-            // Are the expression or the arguments type-adjusted? Then we need the closure
-            if !(is_adjusted(cx, ex) || args.iter().any(|arg| is_adjusted(cx, arg)));
+            // Is the expression type-adjusted, or does an argument carry an adjustment that a named
+            // callee wouldn't re-create? Then we still need the closure. A lone auto-ref / reborrow
+            // is fine, since the callee's signature would insert it identically.
+            if !is_adjusted(cx, ex);
+            if args.iter().all(|arg| adjustments_are_reborrow_only(cx, arg));
 
             let fn_ty = cx.tables.expr_ty(caller);
             if !type_is_unsafe_function(cx, fn_ty);
 
             if compare_inputs(&mut iter_input_pats(decl, body), &mut args.into_iter());
 
+            // Replacing the closure with a bare function path only type-checks if the callee
+            // satisfies the same `Fn*` trait the closure is expected to implement here.
+            let closure_def_id = cx.tcx.hir().local_def_id_from_hir_id(expr.hir_id);
+            if implements_closure_kind(cx, cx.tables.expr_ty(expr), closure_def_id, fn_ty, args);
+
+            // Dropping the closure moves the arguments at the call site instead of when the closure
+            // runs; bail if one of the bound locals is read again later in the enclosing body.
+            if !bindings_used_after(cx, &closure_input_bindings(decl, body), expr);
+
             then {
                 span_lint_and_then(cx, REDUNDANT_CLOSURE, expr.span, "redundant closure found", |db| {
                     if let Some(snippet) = snippet_opt(cx, caller.span) {
@@ -110,7 +168,7 @@ fn check_closure(cx: &LateContext<'_, '_>, expr: &Expr) {
             if let Some(name) = get_ufcs_type_name(cx, method_def_id, &args[0]);
 
             then {
-                span_lint_and_then(cx, REDUNDANT_CLOSURE, expr.span, "redundant closure found", |db| {
+                span_lint_and_then(cx, REDUNDANT_CLOSURE_FOR_METHOD_CALLS, expr.span, "redundant closure found", |db| {
                     db.span_suggestion(
                         expr.span,
                         "remove closure as shown",
@@ -123,6 +181,126 @@ fn check_closure(cx: &LateContext<'_, '_>, expr: &Expr) {
     }
 }
 
+/// Returns `true` when `expr` has no adjustments, or only adjustments a named callee's
+/// signature would re-create identically: a reborrow or auto-ref. Deref coercions, unsizing,
+/// and numeric casts change the value's type and therefore still require the closure.
+fn adjustments_are_reborrow_only(cx: &LateContext<'_, '_>, expr: &Expr) -> bool {
+    cx.tables.adjustments().get(expr.hir_id).map_or(true, |adjustments| {
+        match adjustments.iter().map(|adjustment| &adjustment.kind).collect::<Vec<_>>()[..] {
+            // no adjustment, or a lone auto-ref
+            [] | [Adjust::Borrow(AutoBorrow::Ref(..))] => true,
+            // a reborrow: one builtin deref followed by a borrow
+            [Adjust::Deref(None), Adjust::Borrow(AutoBorrow::Ref(..))] => true,
+            _ => false,
+        }
+    })
+}
+
+/// Collects the binding `HirId`s introduced by the closure's parameters. A later path resolving
+/// (via `Res::Local`) to one of these refers to the same local the closure bound.
+fn closure_input_bindings(decl: &FnDecl, body: &Body) -> Vec<HirId> {
+    iter_input_pats(decl, body)
+        .filter_map(|arg| match arg.pat.node {
+            PatKind::Binding(..) => Some(arg.pat.hir_id),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Walks the body enclosing `closure_expr` and reports whether any of `bindings` is used in the
+/// statements and expressions that follow the closure. Matching is by resolution, not name, so
+/// unrelated same-named locals don't suppress the lint.
+fn bindings_used_after<'a, 'tcx>(cx: &LateContext<'a, 'tcx>, bindings: &[HirId], closure_expr: &Expr) -> bool {
+    if bindings.is_empty() {
+        return false;
+    }
+    let owner = cx.tcx.hir().enclosing_body_owner(closure_expr.hir_id);
+    if let Some(body_id) = cx.tcx.hir().maybe_body_owned_by(owner) {
+        let body = cx.tcx.hir().body(body_id);
+        let mut visitor = UsedAfter {
+            bindings,
+            closure: closure_expr.hir_id,
+            past_closure: false,
+            used: false,
+        };
+        visitor.visit_expr(&body.value);
+        visitor.used
+    } else {
+        false
+    }
+}
+
+struct UsedAfter<'a> {
+    bindings: &'a [HirId],
+    closure: HirId,
+    past_closure: bool,
+    used: bool,
+}
+
+impl<'a, 'tcx> Visitor<'tcx> for UsedAfter<'a> {
+    fn visit_expr(&mut self, expr: &'tcx Expr) {
+        if self.used {
+            return;
+        }
+
+        // Everything visited after the closure node (which is walked in source order) is a later
+        // use; don't descend into the closure body itself.
+        if expr.hir_id == self.closure {
+            self.past_closure = true;
+            return;
+        }
+
+        if_chain! {
+            if self.past_closure;
+            if let ExprKind::Path(QPath::Resolved(None, ref path)) = expr.node;
+            if let Res::Local(hir_id) = path.res;
+            if self.bindings.contains(&hir_id);
+            then {
+                self.used = true;
+                return;
+            }
+        }
+
+        intravisit::walk_expr(self, expr);
+    }
+
+    fn nested_visit_map<'this>(&'this mut self) -> NestedVisitorMap<'this, 'tcx> {
+        NestedVisitorMap::None
+    }
+}
+
+/// Checks that `callee_ty` implements the `Fn`/`FnMut`/`FnOnce` trait matching the kind the
+/// closure of type `closure_ty` was inferred to, for the given `args`. Only when the callee
+/// satisfies the same trait does replacing the closure with the bare path still type-check.
+fn implements_closure_kind<'a, 'tcx>(
+    cx: &LateContext<'a, 'tcx>,
+    closure_ty: ty::Ty<'tcx>,
+    closure_def_id: def_id::DefId,
+    callee_ty: ty::Ty<'tcx>,
+    args: &[Expr],
+) -> bool {
+    let closure_kind = match closure_ty.sty {
+        ty::Closure(_, substs) => substs.closure_kind(closure_def_id, cx.tcx),
+        _ => return false,
+    };
+
+    let fn_trait = match closure_kind {
+        ty::ClosureKind::Fn => cx.tcx.lang_items().fn_trait(),
+        ty::ClosureKind::FnMut => cx.tcx.lang_items().fn_mut_trait(),
+        ty::ClosureKind::FnOnce => cx.tcx.lang_items().fn_once_trait(),
+    };
+    let fn_trait = match fn_trait {
+        Some(trait_id) => trait_id,
+        None => return false,
+    };
+
+    // Use the closure's actual (unadjusted) parameter types: an autoref/reborrow adjustment on an
+    // argument would otherwise ask the callee to satisfy `Fn` for the *reborrowed* type and wrongly
+    // admit e.g. `|x: &mut T| foo(x)` with `foo: fn(&T)`.
+    let input_tys = cx.tcx.mk_tup(args.iter().map(|arg| cx.tables.expr_ty(arg)));
+    implements_trait(cx, callee_ty, fn_trait, &[input_tys])
+}
+
 /// Tries to determine the type for universal function call to be used instead of the closure
 fn get_ufcs_type_name(
     cx: &LateContext<'_, '_>,